@@ -1,25 +1,219 @@
-use chrono::{NaiveDate, Utc};
+use chrono::format::ParseError;
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
-fn is_over_21(dob_string: &str) -> bool {
-    let dob = NaiveDate::parse_from_str(dob_string, "%Y-%m-%d").expect("Invalid date format");
-    let today = Utc::today().naive_utc();
-    let age_21_date = dob.with_year(dob.year() + 21).expect("Invalid date calculation");
-    today >= age_21_date
+/// Ordered list of formats `parse_dob` falls back through after RFC3339.
+const DOB_FORMATS: &[&str] = &[DOB_WIRE_FORMAT, "%Y-%m-%d %H:%M:%S", "%m/%d/%Y"];
+
+/// Canonical wire format for the `dob` field, shared by read and write paths.
+const DOB_WIRE_FORMAT: &str = "%Y-%m-%d";
+
+/// The thresholds surfaced in the output object, keyed by their field name.
+const AGE_RULES: &[(&str, i64)] = &[("is_over_18", 18), ("is_over_21", 21), ("is_over_65", 65)];
+
+/// Serde helper centralizing the `%Y-%m-%d` wire format for `NaiveDate`.
+mod date_fmt {
+    use super::DOB_WIRE_FORMAT;
+    use chrono::NaiveDate;
+    use serde::{self, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S>(date: &NaiveDate, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&date.format(DOB_WIRE_FORMAT).to_string())
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<NaiveDate, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let raw = String::deserialize(deserializer)?;
+        super::parse_dob(&raw).map_err(serde::de::Error::custom)
+    }
 }
 
-fn main() {
-    let input = serde_json::json!({
-        "user": {
-            "name": "Jane Doe",
-            "dob": "2000-04-30"
+/// Parse a date of birth from one of several accepted formats.
+///
+/// RFC3339 timestamps are tried first (real upstream systems emit them), then
+/// the formats in `DOB_FORMATS` in order. The date component is taken from any
+/// datetime match. If nothing parses, the error from the last attempt is
+/// returned.
+fn parse_dob(dob_string: &str) -> Result<NaiveDate, ParseError> {
+    if let Ok(dt) = DateTime::parse_from_rfc3339(dob_string) {
+        return Ok(dt.date_naive());
+    }
+
+    let mut last_err = None;
+    for fmt in DOB_FORMATS {
+        match NaiveDate::parse_from_str(dob_string, fmt) {
+            Ok(date) => return Ok(date),
+            Err(err) => last_err = Some(err),
         }
-    });
+    }
+
+    Err(last_err.expect("DOB_FORMATS is non-empty"))
+}
+
+/// A single age rule: a user passes when `as_of` (defaulting to today) has
+/// reached the `threshold_years`-th anniversary of their date of birth.
+struct AgeGate {
+    threshold_years: i64,
+    as_of: Option<NaiveDate>,
+}
 
-    let user = input["user"].as_object().expect("Invalid user data");
-    let dob = user["dob"].as_str().expect("Invalid date of birth");
-    
-    let is_over_21 = is_over_21(dob);
-    input["user"]["is_over_21"] = serde_json::Value::Bool(is_over_21);
+impl AgeGate {
+    fn new(threshold_years: i64) -> Self {
+        AgeGate { threshold_years, as_of: None }
+    }
+
+    /// Construct a gate pinned to a fixed reference date. Only compiled for
+    /// tests.
+    #[cfg(test)]
+    fn with_as_of(threshold_years: i64, as_of: NaiveDate) -> Self {
+        AgeGate { threshold_years, as_of: Some(as_of) }
+    }
+
+    /// The `threshold_years` anniversary of `dob`. Feb 29 birthdays in
+    /// non-leap target years roll forward to Mar 1.
+    fn anniversary(&self, dob: NaiveDate) -> NaiveDate {
+        let year = dob.year() + self.threshold_years as i32;
+        dob.with_year(year)
+            .unwrap_or_else(|| NaiveDate::from_ymd_opt(year, 3, 1).expect("Mar 1 is always valid"))
+    }
+
+    /// Whether `dob` has crossed this gate as of the reference date.
+    fn passed(&self, dob: NaiveDate, jurisdiction: Option<&str>) -> bool {
+        let as_of = self.as_of.unwrap_or_else(|| today_in(jurisdiction));
+        as_of >= self.anniversary(dob)
+    }
+}
+
+/// Resolve "today" in the given IANA jurisdiction, falling back to UTC when the
+/// field is absent or the zone string is unrecognized.
+fn today_in(jurisdiction: Option<&str>) -> NaiveDate {
+    let now = Utc::now();
+    match jurisdiction.and_then(|name| name.parse::<Tz>().ok()) {
+        Some(tz) => now.with_timezone(&tz).date_naive(),
+        None => now.date_naive(),
+    }
+}
+
+/// A user record as received on the wire and enriched with derived age fields.
+#[derive(Debug, Serialize, Deserialize)]
+struct User {
+    name: String,
+    #[serde(with = "date_fmt")]
+    dob: NaiveDate,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    jurisdiction: Option<String>,
+    /// Derived `is_over_*` fields, flattened into the user object. Driven
+    /// entirely by `AGE_RULES` so adding a rule needs no change here.
+    #[serde(flatten)]
+    derived: BTreeMap<String, bool>,
+}
+
+impl User {
+    /// Fill in the derived age fields from the configured `AGE_RULES`.
+    fn enrich(&mut self) {
+        let jurisdiction = self.jurisdiction.as_deref();
+        for (field, threshold) in AGE_RULES {
+            let passed = AgeGate::new(*threshold).passed(self.dob, jurisdiction);
+            self.derived.insert((*field).to_string(), passed);
+        }
+    }
+}
 
-    println!("{}", serde_json::to_string_pretty(&input).unwrap());
-}
\ No newline at end of file
+/// A batch of user records to enrich in one request.
+#[derive(Debug, Deserialize)]
+struct BatchRequest {
+    users: Vec<serde_json::Value>,
+}
+
+/// A record that failed to deserialize, with its position in the input.
+#[derive(Debug, Serialize)]
+struct RecordError {
+    index: usize,
+    error: String,
+}
+
+/// The outcome of a batch run: the records that enriched successfully and a
+/// diagnostic of the ones that did not.
+#[derive(Debug, Serialize)]
+struct BatchReport {
+    enriched: Vec<User>,
+    errors: Vec<RecordError>,
+}
+
+impl BatchReport {
+    fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+}
+
+impl BatchRequest {
+    fn from_json(json: &str) -> serde_json::Result<Self> {
+        serde_json::from_str(json)
+    }
+
+    /// Enrich every record, collecting per-record failures instead of aborting
+    /// on the first malformed input.
+    fn enrich(self) -> BatchReport {
+        let mut enriched = Vec::new();
+        let mut errors = Vec::new();
+        for (index, record) in self.users.into_iter().enumerate() {
+            match serde_json::from_value::<User>(record) {
+                Ok(mut user) => {
+                    user.enrich();
+                    enriched.push(user);
+                }
+                Err(err) => errors.push(RecordError { index, error: err.to_string() }),
+            }
+        }
+        BatchReport { enriched, errors }
+    }
+}
+
+fn main() {
+    let input = r#"{
+        "users": [
+            { "name": "Jane Doe", "dob": "2000-04-30", "jurisdiction": "America/New_York" },
+            { "name": "John Roe", "dob": "04/30/2000" },
+            { "name": "No Birthday" }
+        ]
+    }"#;
+
+    let report = BatchRequest::from_json(input)
+        .expect("Invalid batch request")
+        .enrich();
+
+    println!("{}", report.to_json().expect("Failed to serialize"));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).expect("valid date")
+    }
+
+    #[test]
+    fn threshold_boundary() {
+        // set_birthday(2020, 5, 15): 21st anniversary of a 1999-05-15 DOB.
+        let dob = date(1999, 5, 15);
+        assert!(!AgeGate::with_as_of(21, date(2020, 5, 14)).passed(dob, None));
+        assert!(AgeGate::with_as_of(21, date(2020, 5, 15)).passed(dob, None));
+    }
+
+    #[test]
+    fn feb_29_rolls_to_mar_1() {
+        let dob = date(2000, 2, 29);
+        // 2021 is not a leap year, so the anniversary rolls to Mar 1.
+        assert_eq!(AgeGate::new(21).anniversary(dob), date(2021, 3, 1));
+        assert!(!AgeGate::with_as_of(21, date(2021, 2, 28)).passed(dob, None));
+        assert!(AgeGate::with_as_of(21, date(2021, 3, 1)).passed(dob, None));
+    }
+}